@@ -7,9 +7,27 @@ use rand::{self, Rng};
 
 use acceleration_structures::quadtree::Quadtree;
 use acceleration_structures::rect;
+use noise::{NoiseFn, OpenSimplex};
 
 const GRAVITY: f32 = -1000.0;
 
+// Fixed-timestep accumulator: integration always advances by `FIXED_DT`,
+// regardless of framerate. `MAX_STEPS_PER_FRAME` bounds the CPU spent
+// draining the accumulator in one frame; the accumulator itself is then
+// clamped to what that many steps can drain, so a lag spike (or sustained
+// sub-15-FPS running) sheds the excess time debt instead of letting the
+// sim fall into ever-growing slow motion.
+const FIXED_DT: f32 = 1.0 / 120.0;
+const MAX_STEPS_PER_FRAME: u32 = 8;
+const MAX_ACCUMULATOR: f32 = MAX_STEPS_PER_FRAME as f32 * FIXED_DT;
+
+// Gravitational constant and Barnes-Hut tuning for the n-body gravity mode.
+// `G` is picked for the simulation's own units, not SI.
+const G: f32 = 60_000.0;
+const BARNES_HUT_THETA: f32 = 0.5;
+const BARNES_HUT_EPSILON: f32 = 4.0;
+const BARNES_HUT_MAX_DEPTH: u32 = 10;
+
 #[rustfmt::skip]
 const ARENA: Rect = Rect {
     min: Vec2 { x: -900.0, y: -500.0 },
@@ -18,9 +36,245 @@ const ARENA: Rect = Rect {
 
 const QUADTREE_OFFSET: f32 = 50.0;
 
+// The "planet" boundary: a closed, lumpy outline sampled from layered
+// OpenSimplex noise around a circle, cached as a polar lookup table of
+// `PLANET_BOUNDARY_SAMPLES` radii. Each `(frequency, amplitude)` pair is one
+// noise layer; higher frequencies add finer bumps on top of the base shape.
+const PLANET_CENTER: Vec2 = Vec2::ZERO;
+const PLANET_BASE_RADIUS: f32 = 650.0;
+const PLANET_BOUNDARY_SAMPLES: usize = 360;
+const PLANET_DEFAULT_SEED: u32 = 1337;
+const PLANET_NOISE_LAYERS: [(f64, f64); 3] = [(4.0, 60.0), (9.0, 25.0), (17.0, 10.0)];
+
+/// The region covered by `QuadtreeRes` and `BarnesHutRes`: the arena padded
+/// by `QUADTREE_OFFSET` so balls resting against the walls still get a
+/// proper node.
+fn quadtree_region() -> rect::Rect {
+    rect::Rect::new(
+        ARENA.min.x - QUADTREE_OFFSET,
+        ARENA.min.y - QUADTREE_OFFSET,
+        ARENA.width() + QUADTREE_OFFSET * 2.0,
+        ARENA.height() + QUADTREE_OFFSET * 2.0,
+    )
+}
+
 #[derive(Resource)]
 struct QuadtreeRes(Quadtree<Entity>);
 
+/// Which gravity model `apply_gravity` uses. `Uniform` is the classic constant
+/// downward pull; `NBody` makes every ball attract every other ball via
+/// `BarnesHutRes`; `RadialToCenter` pulls every ball toward `PLANET_CENTER`,
+/// for use alongside `BoundaryMode::Planet` so material piles up against the
+/// terrain.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default)]
+enum GravityMode {
+    #[default]
+    Uniform,
+    NBody,
+    RadialToCenter,
+}
+
+/// Which shape `solve_constraints` confines balls to. `Rectangular` is the
+/// original `ARENA` clamp; `Planet` confines balls to the interior of
+/// `PlanetBoundaryRes`.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default)]
+enum BoundaryMode {
+    #[default]
+    Rectangular,
+    Planet,
+}
+
+/// A polar lookup table for the planet boundary: `radii[i]` is the distance
+/// from `PLANET_CENTER` to the boundary at angle
+/// `i / PLANET_BOUNDARY_SAMPLES * TAU`. Rebuilding from a seed regenerates
+/// the same lumpy outline deterministically.
+#[derive(Resource)]
+struct PlanetBoundaryRes {
+    seed: u32,
+    radii: [f32; PLANET_BOUNDARY_SAMPLES],
+}
+
+impl PlanetBoundaryRes {
+    fn new(seed: u32) -> Self {
+        let noise = OpenSimplex::new(seed);
+
+        // Sampling 2D noise at a point moving around a circle of radius
+        // `frequency` gives a 1D function of theta that is seamless at the
+        // wrap-around (theta=0 and theta=TAU land on the same input point),
+        // which a direct `noise(frequency * theta)` would not be.
+        let radii = std::array::from_fn(|i| {
+            let theta = i as f64 / PLANET_BOUNDARY_SAMPLES as f64 * std::f64::consts::TAU;
+            let mut radius = PLANET_BASE_RADIUS as f64;
+            for &(frequency, amplitude) in &PLANET_NOISE_LAYERS {
+                let sample = noise.get([theta.cos() * frequency, theta.sin() * frequency]);
+                radius += sample * amplitude;
+            }
+            radius as f32
+        });
+
+        Self { seed, radii }
+    }
+
+    /// The seed this boundary was built from, so it can be logged/shared to
+    /// reproduce the same planet later.
+    fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    fn radius_at(&self, theta: f32) -> f32 {
+        let fraction = theta.rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU;
+        let position = fraction * PLANET_BOUNDARY_SAMPLES as f32;
+
+        let i0 = position.floor() as usize % PLANET_BOUNDARY_SAMPLES;
+        let i1 = (i0 + 1) % PLANET_BOUNDARY_SAMPLES;
+        let t = position.fract();
+
+        self.radii[i0] * (1.0 - t) + self.radii[i1] * t
+    }
+}
+
+impl Default for PlanetBoundaryRes {
+    fn default() -> Self {
+        Self::new(PLANET_DEFAULT_SEED)
+    }
+}
+
+/// A mass/center-of-mass aggregation tree, rebuilt from scratch once per
+/// substep from the balls' current positions. It mirrors the spatial
+/// subdivision that `Quadtree` (from `acceleration_structures`) already does
+/// for collisions, but tracks aggregate mass instead of entries, which that
+/// external type has no concept of, so it lives here as its own tree rather
+/// than as an extension of `Quadtree` itself.
+#[derive(Default)]
+enum BarnesHutNode {
+    #[default]
+    Empty,
+    /// A single body, or a cluster too deep to subdivide further; always
+    /// treated as one point mass.
+    Leaf {
+        mass: f32,
+        center_of_mass: Vec2,
+    },
+    Internal {
+        mass: f32,
+        center_of_mass: Vec2,
+        region: rect::Rect,
+        children: Box<[BarnesHutNode; 4]>,
+    },
+}
+
+#[derive(Resource, Default)]
+struct BarnesHutRes(BarnesHutNode);
+
+/// Leftover simulation time not yet consumed by a fixed-timestep step.
+#[derive(Resource, Default)]
+struct PhysicsAccumulator(f32);
+
+/// Holds the bytes of the most recently taken `take_snapshot`, if any, so it
+/// can later be handed to `restore_snapshot`.
+#[derive(Resource, Default)]
+struct SnapshotSlot(Option<Vec<u8>>);
+
+fn build_barnes_hut(region: rect::Rect, bodies: &[(Vec2, f32)], depth: u32) -> BarnesHutNode {
+    if bodies.is_empty() {
+        return BarnesHutNode::Empty;
+    }
+
+    let mut total_mass = 0.0;
+    let mut weighted_position = Vec2::ZERO;
+    for &(position, mass) in bodies {
+        total_mass += mass;
+        weighted_position += position * mass;
+    }
+    let center_of_mass = weighted_position / total_mass;
+
+    if bodies.len() == 1 || depth >= BARNES_HUT_MAX_DEPTH {
+        return BarnesHutNode::Leaf {
+            mass: total_mass,
+            center_of_mass,
+        };
+    }
+
+    let mid_x = region.x + region.w / 2.0;
+    let mid_y = region.y + region.h / 2.0;
+    let quadrants = [
+        rect::Rect::new(region.x, region.y, region.w / 2.0, region.h / 2.0),
+        rect::Rect::new(mid_x, region.y, region.w / 2.0, region.h / 2.0),
+        rect::Rect::new(region.x, mid_y, region.w / 2.0, region.h / 2.0),
+        rect::Rect::new(mid_x, mid_y, region.w / 2.0, region.h / 2.0),
+    ];
+
+    let mut buckets: [Vec<(Vec2, f32)>; 4] = Default::default();
+    for &(position, mass) in bodies {
+        let index = match (position.x >= mid_x, position.y >= mid_y) {
+            (false, false) => 0,
+            (true, false) => 1,
+            (false, true) => 2,
+            (true, true) => 3,
+        };
+        buckets[index].push((position, mass));
+    }
+
+    let children = std::array::from_fn(|i| build_barnes_hut(quadrants[i], &buckets[i], depth + 1));
+
+    BarnesHutNode::Internal {
+        mass: total_mass,
+        center_of_mass,
+        region,
+        children: Box::new(children),
+    }
+}
+
+/// Walks the tree from `node`, approximating far-away subtrees as a single
+/// point mass once `region.width / distance < theta`. Returns an
+/// acceleration (not a force): since a = G*m/d^2 is independent of the
+/// attracted body's own mass, there is nothing to divide out.
+fn approx_force(node: &BarnesHutNode, point: Vec2, theta: f32) -> Vec2 {
+    match node {
+        BarnesHutNode::Empty => Vec2::ZERO,
+        BarnesHutNode::Leaf {
+            mass,
+            center_of_mass,
+        } => point_mass_acceleration(point, *center_of_mass, *mass),
+        BarnesHutNode::Internal {
+            mass,
+            center_of_mass,
+            region,
+            children,
+        } => {
+            let distance = center_of_mass.distance(point);
+            if region.w / distance < theta {
+                point_mass_acceleration(point, *center_of_mass, *mass)
+            } else {
+                children
+                    .iter()
+                    .map(|child| approx_force(child, point, theta))
+                    .sum()
+            }
+        }
+    }
+}
+
+fn point_mass_acceleration(point: Vec2, other: Vec2, mass: f32) -> Vec2 {
+    let delta = other - point;
+    // Softened denominator avoids a singularity when bodies overlap; when
+    // `other` is the point's own center of mass, `normalize_or_zero` yields
+    // zero, so a single-body leaf never attracts the body it holds. A leaf
+    // capped at `BARNES_HUT_MAX_DEPTH` that holds several bodies still
+    // includes the attracted body's own mass in its pull.
+    let distance_squared = delta.length_squared() + BARNES_HUT_EPSILON * BARNES_HUT_EPSILON;
+    delta.normalize_or_zero() * G * mass / distance_squared
+}
+
+fn rebuild_barnes_hut(mass_tree: &mut BarnesHutRes, balls: &Query<(&mut VerletObject, &Ball)>) {
+    let bodies: Vec<(Vec2, f32)> = balls
+        .iter()
+        .map(|(verlet_object, ball)| (verlet_object.position_current, ball.radius * ball.radius))
+        .collect();
+
+    mass_tree.0 = build_barnes_hut(quadtree_region(), &bodies, 0);
+}
+
 #[derive(Component)]
 struct QuadtreeMesh;
 
@@ -36,21 +290,51 @@ struct Ball {
     radius: f32,
 }
 
+// A rope/chain segment spawned between the last two balls while chaining is
+// held, so holding the chain key while spawning balls builds a rope out of
+// them.
+const LINK_REST_LENGTH: f32 = 40.0;
+const LINK_STIFFNESS: f32 = 0.5;
+
+/// A distance constraint between two `VerletObject` entities, solved once
+/// per substep by `solve_links`. Lives on its own entity, not on either
+/// endpoint, since it references both.
+#[derive(Component)]
+struct Link {
+    entity_a: Entity,
+    entity_b: Entity,
+    target_distance: f32,
+    stiffness: f32,
+}
+
+/// The most recently spawned ball, so holding the chain key links each new
+/// ball to the one before it.
+#[derive(Resource, Default)]
+struct LastSpawnedBall(Option<Entity>);
+
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         .insert_resource(ClearColor(Color::rgb(0.2, 0.2, 0.2)))
-        .insert_resource(QuadtreeRes(Quadtree::new(
-            rect::Rect::new(
-                ARENA.min.x - QUADTREE_OFFSET,
-                ARENA.min.y - QUADTREE_OFFSET,
-                ARENA.width() + QUADTREE_OFFSET * 2.0,
-                ARENA.height() + QUADTREE_OFFSET * 2.0,
-            ),
-            5,
-        )))
+        .insert_resource(QuadtreeRes(Quadtree::new(quadtree_region(), 5)))
+        .insert_resource(GravityMode::default())
+        .insert_resource(BarnesHutRes::default())
+        .insert_resource(PhysicsAccumulator::default())
+        .insert_resource(SnapshotSlot::default())
+        .insert_resource(BoundaryMode::default())
+        .insert_resource(PlanetBoundaryRes::default())
+        .insert_resource(LastSpawnedBall::default())
         .add_systems(Startup, setup)
-        .add_systems(Update, spawn_ball)
+        .add_systems(
+            Update,
+            (
+                spawn_ball,
+                toggle_gravity_mode,
+                handle_snapshot_input,
+                toggle_boundary_mode,
+                regenerate_planet_boundary,
+            ),
+        )
         .add_systems(Update, (update_physics, update_transforms).chain())
         .add_systems(Last, update_quadtree_mesh)
         .run();
@@ -103,6 +387,7 @@ fn spawn_ball(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut quadtree: ResMut<QuadtreeRes>,
+    mut last_spawned: ResMut<LastSpawnedBall>,
     input: Res<ButtonInput<KeyCode>>,
     balls: Query<Entity, With<Transform>>,
 ) {
@@ -143,30 +428,104 @@ fn spawn_ball(
         ball_entity,
         rect::Rect::new_centered(0.0, 0.0, radius / 2.0, radius / 2.0),
     );
+
+    if input.pressed(KeyCode::KeyC) {
+        if let Some(previous) = last_spawned.0 {
+            commands.spawn(Link {
+                entity_a: previous,
+                entity_b: ball_entity,
+                target_distance: LINK_REST_LENGTH,
+                stiffness: LINK_STIFFNESS,
+            });
+        }
+    }
+    last_spawned.0 = Some(ball_entity);
 }
 
 fn update_physics(
+    mut commands: Commands,
     time: Res<Time>,
+    mode: Res<GravityMode>,
+    boundary_mode: Res<BoundaryMode>,
+    boundary: Res<PlanetBoundaryRes>,
+    mut accumulator: ResMut<PhysicsAccumulator>,
     mut quadtree: ResMut<QuadtreeRes>,
+    mut mass_tree: ResMut<BarnesHutRes>,
     mut balls: Query<(&mut VerletObject, &Ball)>,
+    links: Query<(Entity, &Link)>,
 ) {
-    let dt = time.delta().as_secs_f32();
+    accumulator.0 += time.delta().as_secs_f32();
 
-    let num_substeps = 5;
-
-    let sub_dt = dt / num_substeps as f32;
-    for _ in 0..num_substeps {
-        apply_gravity(&mut balls);
-        update_position(sub_dt, &mut balls);
+    let mut steps = 0;
+    while accumulator.0 >= FIXED_DT && steps < MAX_STEPS_PER_FRAME {
+        rebuild_barnes_hut(&mut mass_tree, &balls);
+        apply_gravity(*mode, &mass_tree.0, &mut balls);
+        update_position(FIXED_DT, &mut balls);
         update_quadtree(&mut quadtree, &balls);
         solve_colisions(&mut balls, &quadtree);
-        solve_constraints(&mut balls);
+        solve_links(&mut commands, &links, &mut balls);
+        solve_constraints(*boundary_mode, &boundary, &mut balls);
+
+        accumulator.0 -= FIXED_DT;
+        steps += 1;
     }
+
+    // `MAX_STEPS_PER_FRAME` only bounds the work done this frame, not the
+    // leftover time debt: below ~15 FPS the loop above can't drain as much
+    // as keeps arriving, and an uncapped accumulator would grow forever,
+    // pushing the sim into ever-increasing slow motion. Shed anything the
+    // loop couldn't keep up with instead.
+    accumulator.0 = accumulator.0.min(MAX_ACCUMULATOR);
 }
 
-fn apply_gravity(verlet_objects: &mut Query<(&mut VerletObject, &Ball)>) {
+fn toggle_gravity_mode(mut mode: ResMut<GravityMode>, input: Res<ButtonInput<KeyCode>>) {
+    if !input.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+
+    *mode = match *mode {
+        GravityMode::Uniform => GravityMode::NBody,
+        GravityMode::NBody => GravityMode::RadialToCenter,
+        GravityMode::RadialToCenter => GravityMode::Uniform,
+    };
+}
+
+fn toggle_boundary_mode(mut mode: ResMut<BoundaryMode>, input: Res<ButtonInput<KeyCode>>) {
+    if !input.just_pressed(KeyCode::KeyP) {
+        return;
+    }
+
+    *mode = match *mode {
+        BoundaryMode::Rectangular => BoundaryMode::Planet,
+        BoundaryMode::Planet => BoundaryMode::Rectangular,
+    };
+}
+
+fn regenerate_planet_boundary(mut boundary: ResMut<PlanetBoundaryRes>, input: Res<ButtonInput<KeyCode>>) {
+    if !input.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+
+    *boundary = PlanetBoundaryRes::new(rand::thread_rng().gen());
+    info!("regenerated planet boundary with seed {}", boundary.seed());
+}
+
+fn apply_gravity(
+    mode: GravityMode,
+    mass_tree: &BarnesHutNode,
+    verlet_objects: &mut Query<(&mut VerletObject, &Ball)>,
+) {
     for (mut verlet_object, _) in verlet_objects.iter_mut() {
-        verlet_object.acceleration.y += GRAVITY;
+        verlet_object.acceleration += match mode {
+            GravityMode::Uniform => Vec2::new(0.0, GRAVITY),
+            GravityMode::NBody => {
+                approx_force(mass_tree, verlet_object.position_current, BARNES_HUT_THETA)
+            }
+            GravityMode::RadialToCenter => {
+                let offset = PLANET_CENTER - verlet_object.position_current;
+                offset.normalize_or_zero() * GRAVITY.abs()
+            }
+        };
     }
 }
 
@@ -181,7 +540,18 @@ fn update_position(dt: f32, balls: &mut Query<(&mut VerletObject, &Ball)>) {
     }
 }
 
-fn solve_constraints(balls: &mut Query<(&mut VerletObject, &Ball)>) {
+fn solve_constraints(
+    mode: BoundaryMode,
+    boundary: &PlanetBoundaryRes,
+    balls: &mut Query<(&mut VerletObject, &Ball)>,
+) {
+    match mode {
+        BoundaryMode::Rectangular => solve_rectangular_constraint(balls),
+        BoundaryMode::Planet => solve_planet_constraint(boundary, balls),
+    }
+}
+
+fn solve_rectangular_constraint(balls: &mut Query<(&mut VerletObject, &Ball)>) {
     for (mut verlet_object, ball) in balls.iter_mut() {
         let r = ball.radius;
 
@@ -203,6 +573,73 @@ fn solve_constraints(balls: &mut Query<(&mut VerletObject, &Ball)>) {
     }
 }
 
+fn solve_planet_constraint(
+    boundary: &PlanetBoundaryRes,
+    balls: &mut Query<(&mut VerletObject, &Ball)>,
+) {
+    for (mut verlet_object, ball) in balls.iter_mut() {
+        let offset = verlet_object.position_current - PLANET_CENTER;
+        let distance = offset.length();
+        if distance <= f32::EPSILON {
+            continue;
+        }
+
+        let theta = offset.y.atan2(offset.x);
+        let limit = boundary.radius_at(theta) - ball.radius;
+
+        if distance > limit {
+            verlet_object.position_current = PLANET_CENTER + offset / distance * limit;
+        }
+    }
+}
+
+fn solve_links(
+    commands: &mut Commands,
+    links: &Query<(Entity, &Link)>,
+    balls: &mut Query<(&mut VerletObject, &Ball)>,
+) {
+    let mut corrections = Vec::new();
+
+    for (link_entity, link) in links.iter() {
+        // Either endpoint may have been despawned since the link was
+        // created — links are runtime-spawnable with no lifecycle tied to
+        // their endpoints. Drop the orphaned link rather than panicking.
+        let Ok((verlet_a, ball_a)) = balls.get(link.entity_a) else {
+            commands.entity(link_entity).despawn();
+            continue;
+        };
+        let Ok((verlet_b, ball_b)) = balls.get(link.entity_b) else {
+            commands.entity(link_entity).despawn();
+            continue;
+        };
+
+        let delta = verlet_b.position_current - verlet_a.position_current;
+        let distance = delta.length();
+        if distance <= f32::EPSILON {
+            continue;
+        }
+
+        let direction = delta / distance;
+        let error = distance - link.target_distance;
+
+        // mass = radius^2, same convention as the Barnes-Hut gravity mode;
+        // each endpoint's share of the correction is weighted by the
+        // other's mass, so a heavy/pinned object moves less.
+        let mass_a = ball_a.radius * ball_a.radius;
+        let mass_b = ball_b.radius * ball_b.radius;
+        let total_mass = mass_a + mass_b;
+
+        let correction = direction * error * link.stiffness;
+        corrections.push((link.entity_a, correction * (mass_b / total_mass)));
+        corrections.push((link.entity_b, -correction * (mass_a / total_mass)));
+    }
+
+    for (entity, correction) in corrections {
+        let (mut verlet_object, _) = balls.get_mut(entity).unwrap();
+        verlet_object.position_current += correction;
+    }
+}
+
 fn solve_colisions(balls: &mut Query<(&mut VerletObject, &Ball)>, quadtree: &ResMut<QuadtreeRes>) {
     let mut new_positions = Vec::new();
     for entry in quadtree.0.entries() {
@@ -262,6 +699,8 @@ fn update_quadtree_mesh(
     quad_mesh: Query<&Mesh2dHandle, With<QuadtreeMesh>>,
     mut quadtree: ResMut<QuadtreeRes>,
     verlet_objects: Query<(&VerletObject, &Ball)>,
+    boundary_mode: Res<BoundaryMode>,
+    boundary: Res<PlanetBoundaryRes>,
 ) {
     let quad_mesh_handle = quad_mesh.single();
     let quad_mesh = meshes.get_mut(quad_mesh_handle.0.id()).unwrap();
@@ -309,6 +748,22 @@ fn update_quadtree_mesh(
         ])
     }
 
+    if *boundary_mode == BoundaryMode::Planet {
+        for i in 0..PLANET_BOUNDARY_SAMPLES {
+            let theta_a = i as f32 / PLANET_BOUNDARY_SAMPLES as f32 * std::f32::consts::TAU;
+            let theta_b =
+                (i + 1) as f32 / PLANET_BOUNDARY_SAMPLES as f32 * std::f32::consts::TAU;
+
+            let a = PLANET_CENTER + Vec2::new(theta_a.cos(), theta_a.sin()) * boundary.radii[i];
+            let b = PLANET_CENTER
+                + Vec2::new(theta_b.cos(), theta_b.sin())
+                    * boundary.radii[(i + 1) % PLANET_BOUNDARY_SAMPLES];
+
+            vertices.push([a.x, a.y, 0.0]);
+            vertices.push([b.x, b.y, 0.0]);
+        }
+    }
+
     quad_mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
 }
 
@@ -329,3 +784,165 @@ fn update_quadtree(
         entry.move_entry(new_region);
     }
 }
+
+/// One ball's Verlet state plus its collision radius: everything needed to
+/// restore it exactly. Mass for gravity is derived from `radius`, so nothing
+/// else needs saving.
+#[derive(Clone, Copy)]
+struct BallSnapshot {
+    position_current: Vec2,
+    position_old: Vec2,
+    acceleration: Vec2,
+    radius: f32,
+}
+
+impl BallSnapshot {
+    const BYTE_LEN: usize = std::mem::size_of::<f32>() * 7;
+
+    fn write_to(self, out: &mut Vec<u8>) {
+        for component in [
+            self.position_current.x,
+            self.position_current.y,
+            self.position_old.x,
+            self.position_old.y,
+            self.acceleration.x,
+            self.acceleration.y,
+            self.radius,
+        ] {
+            out.extend_from_slice(&component.to_le_bytes());
+        }
+    }
+
+    fn read_from(bytes: &[u8]) -> Self {
+        let mut floats = bytes
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes(c.try_into().unwrap()));
+        let mut next = || floats.next().unwrap();
+
+        Self {
+            position_current: Vec2::new(next(), next()),
+            position_old: Vec2::new(next(), next()),
+            acceleration: Vec2::new(next(), next()),
+            radius: next(),
+        }
+    }
+}
+
+/// Byte length of the snapshot header that precedes the per-ball data: just
+/// the fixed-timestep accumulator.
+const SNAPSHOT_HEADER_LEN: usize = std::mem::size_of::<f32>();
+
+/// Serializes the fixed-timestep accumulator and every ball's Verlet state
+/// and radius into a compact byte buffer, in query iteration order.
+/// `QuadtreeRes` and `BarnesHutRes` are not part of the snapshot:
+/// `restore_snapshot` rebuilds both from scratch from the restored
+/// positions (`BarnesHutRes` is rebuilt that way every substep anyway;
+/// `QuadtreeRes` is otherwise only updated entry by entry by
+/// `update_quadtree`). The accumulator *is* captured:
+/// it is live stepping state for the fixed-timestep loop (how much leftover
+/// time carries into the next frame's substep count), not something
+/// `update_physics` derives fresh each call.
+fn take_snapshot(
+    accumulator: &PhysicsAccumulator,
+    balls: &Query<(Entity, &mut VerletObject, &mut Ball)>,
+) -> Vec<u8> {
+    let mut bytes =
+        Vec::with_capacity(SNAPSHOT_HEADER_LEN + balls.iter().len() * BallSnapshot::BYTE_LEN);
+    bytes.extend_from_slice(&accumulator.0.to_le_bytes());
+
+    for (_, verlet_object, ball) in balls.iter() {
+        BallSnapshot {
+            position_current: verlet_object.position_current,
+            position_old: verlet_object.position_old,
+            acceleration: verlet_object.acceleration,
+            radius: ball.radius,
+        }
+        .write_to(&mut bytes);
+    }
+
+    bytes
+}
+
+/// Restores a buffer produced by `take_snapshot`, including the
+/// fixed-timestep accumulator. The set of ball entities must be unchanged
+/// since the snapshot was taken (same entities, same iteration order, none
+/// spawned or despawned in between) — rollback and record/replay both hold
+/// this invariant by construction. With that invariant held, `snapshot -> N
+/// steps -> restore -> N steps` reproduces the same snapshot. If the number
+/// of balls differs from the snapshot's, the restore is declined (logged,
+/// not panicked) and the world is left untouched. Only the count is
+/// checked: a different set of entities with the same count is restored
+/// in iteration order.
+fn restore_snapshot(
+    bytes: &[u8],
+    balls: &mut Query<(Entity, &mut VerletObject, &mut Ball)>,
+    quadtree: &mut ResMut<QuadtreeRes>,
+    mass_tree: &mut ResMut<BarnesHutRes>,
+    accumulator: &mut PhysicsAccumulator,
+) {
+    let ball_count = balls.iter().len();
+    if bytes.len() != SNAPSHOT_HEADER_LEN + ball_count * BallSnapshot::BYTE_LEN {
+        warn!(
+            "declining to restore snapshot: it was taken with a different number of balls \
+             than currently exist; restore requires the exact same ball entities that \
+             existed at snapshot time"
+        );
+        return;
+    }
+
+    let (header, body) = bytes.split_at(SNAPSHOT_HEADER_LEN);
+    accumulator.0 = f32::from_le_bytes(header.try_into().unwrap());
+
+    let mut rebuilt = Quadtree::new(quadtree_region(), 5);
+    let mut bodies = Vec::with_capacity(ball_count);
+
+    let snapshots = body
+        .chunks_exact(BallSnapshot::BYTE_LEN)
+        .map(BallSnapshot::read_from);
+
+    for ((entity, mut verlet_object, mut ball), snapshot) in balls.iter_mut().zip(snapshots) {
+        verlet_object.position_current = snapshot.position_current;
+        verlet_object.position_old = snapshot.position_old;
+        verlet_object.acceleration = snapshot.acceleration;
+        ball.radius = snapshot.radius;
+
+        rebuilt.insert(
+            entity,
+            rect::Rect::new_centered(
+                snapshot.position_current.x,
+                snapshot.position_current.y,
+                snapshot.radius * 2.0,
+                snapshot.radius * 2.0,
+            ),
+        );
+        bodies.push((snapshot.position_current, snapshot.radius * snapshot.radius));
+    }
+
+    quadtree.0 = rebuilt;
+    mass_tree.0 = build_barnes_hut(quadtree_region(), &bodies, 0);
+}
+
+/// F5 takes a snapshot of the current world into `SnapshotSlot`; F9 restores
+/// the most recently taken one, rolling the simulation back to that frame.
+fn handle_snapshot_input(
+    input: Res<ButtonInput<KeyCode>>,
+    mut slot: ResMut<SnapshotSlot>,
+    mut quadtree: ResMut<QuadtreeRes>,
+    mut mass_tree: ResMut<BarnesHutRes>,
+    mut accumulator: ResMut<PhysicsAccumulator>,
+    mut balls: Query<(Entity, &mut VerletObject, &mut Ball)>,
+) {
+    if input.just_pressed(KeyCode::F5) {
+        slot.0 = Some(take_snapshot(&accumulator, &balls));
+    } else if input.just_pressed(KeyCode::F9) {
+        if let Some(bytes) = slot.0.clone() {
+            restore_snapshot(
+                &bytes,
+                &mut balls,
+                &mut quadtree,
+                &mut mass_tree,
+                &mut accumulator,
+            );
+        }
+    }
+}